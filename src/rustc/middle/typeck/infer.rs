@@ -105,6 +105,16 @@ because the type variable `T` is merged with the type variable for
 `X`, and thus inherits its UB/LB of `@mut int`.  This leaves no
 flexibility for `T` to later adjust to accommodate `@int`.
 
+To recover that flexibility, `vars()` no longer merges A and B as
+its first resort.  Instead it records a subtyping *edge* `A <: B` in
+a small graph kept alongside the bounds (see `var_edges`) and pushes
+the existing bounds of A and B along that edge to a fixed point:
+narrowing an upper bound flows down to the variables known to be
+below it, and raising a lower bound flows up to the variables known
+to be above it.  A and B are only collapsed into a single variable
+if recording the edge would close a cycle in this graph, since the
+propagation above assumes the graph is acyclic.
+
 ## Transactional support
 
 Whenever we adjust merge variables or adjust their bounds, we always
@@ -150,6 +160,7 @@ import middle::ty;
 import middle::ty::{ty_vid, region_vid, vid};
 import syntax::ast;
 import syntax::ast::{ret_style};
+import syntax::codemap::span;
 import util::ppaux::{ty_to_str, mt_to_str};
 import result::{result, extensions, ok, err, map, map2, iter2};
 import ty::type_is_bot;
@@ -168,6 +179,7 @@ export resolve_deep_var;
 export ty_and_region_var_methods;
 export compare_tys;
 export fixup_err, fixup_err_to_str;
+export snapshot;
 
 // Extra information needed to perform an assignment that may borrow.
 // The `expr_id` is the is of the expression whose type is being
@@ -187,46 +199,101 @@ enum var_value<V:copy, T:copy> {
     bounded(bounds<T>)
 }
 
+// The subtyping-graph edges for a single variable: the variables
+// known to be below it (`lb_vars`) and above it (`ub_vars`).  These
+// are kept separate from `bounds` because, unlike the concrete
+// `lb`/`ub` bounds, they never get resolved away---they just record
+// which other variables a bound change must be pushed to.
+type var_edges<V:copy> = {lb_vars: [V], ub_vars: [V]};
+
 type vals_and_bindings<V:copy, T:copy> = {
     vals: smallintmap<var_value<V, T>>,
-    mut bindings: [(V, var_value<V, T>)]
+    mut bindings: [(V, var_value<V, T>)],
+    edges: smallintmap<var_edges<V>>,
+    mut edge_log: [(V, var_edges<V>)]
 };
 
+// A pending assignability (coercion) obligation: we could not yet
+// decide whether `a` can be assigned to `b` under `anmnt` because
+// neither side had a usable bound, so we record it against the
+// variable that was missing a bound and re-check it once that
+// variable is resolved further.  See the comment on `assign_tys`.
+type assign_obligation = {anmnt: assignment, a: ty::t, b: ty::t};
+
 enum infer_ctxt = @{
     tcx: ty::ctxt,
     vb: vals_and_bindings<ty::ty_vid, ty::t>,
     rb: vals_and_bindings<ty::region_vid, ty::region>,
+    mut assign_obligations: smallintmap<[assign_obligation]>,
+
+    // Undo log for `assign_obligations`: the vid and its prior list of
+    // obligations, pushed just before each mutation, mirroring
+    // `vals_and_bindings.bindings`/`edge_log`. Without this, an
+    // obligation recorded while a `try()` is open (e.g. the
+    // speculative `sub` in `vars`, or any future speculative-coercion
+    // caller) would survive a subsequent rollback and later get
+    // replayed against a variable pair that belonged to the abandoned
+    // branch.
+    mut assign_obligation_log: [(ty_vid, [assign_obligation])],
 
     // For keeping track of existing type/region variables.
     ty_var_counter: @mut uint,
     region_var_counter: @mut uint,
+
+    // The span of the expression/type that caused each variable to be
+    // created, keyed by `to_uint()` of its id.  Recorded purely for
+    // diagnostics: when a variable is later reported as unresolved or
+    // cyclic, this lets the error point back at the position that
+    // introduced it instead of just naming an opaque variable id.
+    mut ty_var_spans: smallintmap<span>,
+    mut region_var_spans: smallintmap<span>,
+
+    // How many `start_snapshot`s are currently open (i.e. have not
+    // yet been matched by a `rollback_to`/`commit_from`).  Used so
+    // `commit_from` can tell when it's closing the outermost
+    // transaction, at which point the undo logs can be dropped for
+    // good instead of growing for the lifetime of the context.
+    mut snapshot_depth: uint,
 };
 
 enum fixup_err {
-    unresolved_ty(ty_vid),
-    cyclic_ty(ty_vid),
-    unresolved_region(region_vid),
-    cyclic_region(region_vid)
+    unresolved_ty(ty_vid, span),
+    cyclic_ty(ty_vid, span),
+    unresolved_region(region_vid, span),
+    cyclic_region(region_vid, span)
 }
 
 fn fixup_err_to_str(f: fixup_err) -> str {
     alt f {
-      unresolved_ty(_) { "unconstrained type" }
-      cyclic_ty(_) { "cyclic type of infinite size" }
-      unresolved_region(_) { "unconstrained region" }
-      cyclic_region(_) { "cyclic region" }
+      unresolved_ty(_, _) { "unconstrained type" }
+      cyclic_ty(_, _) { "cyclic type of infinite size" }
+      unresolved_region(_, _) { "unconstrained region" }
+      cyclic_region(_, _) { "cyclic region" }
     }
 }
 
 type ures = result::result<(), ty::type_err>;
-type fres<T> = result::result<T, fixup_err>;
+
+// Unlike most `result`s in this file, the error side is a *vector* of
+// `fixup_err`: a single call to `resolve` may walk over many
+// unresolved or cyclic variables, and we want to report every one of
+// them in a single diagnostic pass rather than stopping at the first
+// and forcing the caller to fix-and-recompile-and-hit-the-next-one.
+type fres<T> = result::result<T, [fixup_err]>;
 
 fn new_infer_ctxt(tcx: ty::ctxt) -> infer_ctxt {
     infer_ctxt(@{tcx: tcx,
-                 vb: {vals: smallintmap::mk(), mut bindings: []},
-                 rb: {vals: smallintmap::mk(), mut bindings: []},
+                 vb: {vals: smallintmap::mk(), mut bindings: [],
+                      edges: smallintmap::mk(), mut edge_log: []},
+                 rb: {vals: smallintmap::mk(), mut bindings: [],
+                      edges: smallintmap::mk(), mut edge_log: []},
+                 mut assign_obligations: smallintmap::mk(),
+                 mut assign_obligation_log: [],
                  ty_var_counter: @mut 0u,
-                 region_var_counter: @mut 0u})}
+                 region_var_counter: @mut 0u,
+                 mut ty_var_spans: smallintmap::mk(),
+                 mut region_var_spans: smallintmap::mk(),
+                 mut snapshot_depth: 0u})}
 
 fn mk_subty(cx: infer_ctxt, a: ty::t, b: ty::t) -> ures {
     #debug["mk_subty(%s <: %s)", a.to_str(cx), b.to_str(cx)];
@@ -386,43 +453,142 @@ fn uok() -> ures {
     ok(())
 }
 
-impl methods for infer_ctxt {
-    fn commit<T,E>(f: fn() -> result<T,E>) -> result<T,E> {
+// Applies `f` to each element of `v` in turn, stopping at (and
+// returning) the first error.
+fn iter2_ures<V:copy>(v: [V], f: fn(V) -> ures) -> ures {
+    for v.each {|x|
+        alt f(x) {
+          ok(()) { }
+          e { ret e; }
+        }
+    }
+    uok()
+}
 
-        assert self.vb.bindings.len() == 0u;
-        assert self.rb.bindings.len() == 0u;
+// Returns `vs` with every occurrence of `old` replaced by `new`,
+// de-duplicating as it goes (used when migrating one variable's edges
+// onto another after a merge).
+fn replace_var<V:copy vid>(vs: [V], old: V, new: V) -> [V] {
+    let mut result = [];
+    for vs.each {|v|
+        let v2 = if v == old { new } else { v };
+        if !vec::contains(result, v2) {
+            result += [v2];
+        }
+    }
+    result
+}
 
-        let r <- self.try(f);
+// Returns the de-duplicated union of `a` and `b`.
+fn union_vars<V:copy vid>(a: [V], b: [V]) -> [V] {
+    let mut result = a;
+    for b.each {|v|
+        if !vec::contains(result, v) {
+            result += [v];
+        }
+    }
+    result
+}
 
-        // TODO---could use a vec::clear() that ran destructors but kept
-        // the vec at its currently allocated length
-        self.vb.bindings = [];
-        self.rb.bindings = [];
+fn rollback_vb_to<V:copy vid, T:copy>(
+    vb: vals_and_bindings<V, T>, len: uint, edge_len: uint) {
 
-        ret r;
+    while vb.bindings.len() != len {
+        let (vid, old_v) = vec::pop(vb.bindings);
+        vb.vals.insert(vid.to_uint(), old_v);
     }
 
-    fn try<T,E>(f: fn() -> result<T,E>) -> result<T,E> {
+    while vb.edge_log.len() != edge_len {
+        let (vid, old_es) = vec::pop(vb.edge_log);
+        vb.edges.insert(vid.to_uint(), old_es);
+    }
+}
 
-        fn rollback_to<V:copy vid, T:copy>(
-            vb: vals_and_bindings<V, T>, len: uint) {
+// Drops the undo logs entirely, keeping whatever values/edges are
+// currently recorded in `vb.vals`/`vb.edges`.  Safe to call only when
+// no enclosing transaction could ever want to roll back past this
+// point, i.e. once the snapshot stack has unwound to depth 0.
+fn truncate_vb_log<V:copy vid, T:copy>(vb: vals_and_bindings<V, T>) {
+    vb.bindings = [];
+    vb.edge_log = [];
+}
 
-            while vb.bindings.len() != len {
-                let (vid, old_v) = vec::pop(vb.bindings);
-                vb.vals.insert(vid.to_uint(), old_v);
-            }
+// A marker recording how far along the undo logs we were at the
+// point `start_snapshot()` was called.  `rollback_to` undoes
+// everything pushed since; `commit_from` just forgets the marker,
+// leaving those changes in place (and, once every snapshot has been
+// closed out, drops the now-useless undo logs entirely via
+// `infer_ctxt.snapshot_depth`).  Because this is just a pair of
+// lengths (rather than an assertion that the logs are empty), these
+// can be nested arbitrarily deeply: a `try` can be wrapped inside
+// another `try`, which is needed for things like speculatively
+// attempting several method-receiver coercions and keeping only the
+// winner.
+type snapshot = {
+    ty_var_bindings_len: uint,
+    ty_var_edges_len: uint,
+    region_var_bindings_len: uint,
+    region_var_edges_len: uint,
+    assign_obligation_log_len: uint
+};
+
+impl methods for infer_ctxt {
+    fn start_snapshot() -> snapshot {
+        self.snapshot_depth += 1u;
+        {ty_var_bindings_len: self.vb.bindings.len(),
+         ty_var_edges_len: self.vb.edge_log.len(),
+         region_var_bindings_len: self.rb.bindings.len(),
+         region_var_edges_len: self.rb.edge_log.len(),
+         assign_obligation_log_len: self.assign_obligation_log.len()}
+    }
+
+    fn rollback_to(snapshot: snapshot) {
+        rollback_vb_to(self.vb, snapshot.ty_var_bindings_len,
+                       snapshot.ty_var_edges_len);
+        rollback_vb_to(self.rb, snapshot.region_var_bindings_len,
+                       snapshot.region_var_edges_len);
+        while self.assign_obligation_log.len() !=
+                snapshot.assign_obligation_log_len {
+            let (vid, old_obs) = vec::pop(self.assign_obligation_log);
+            self.assign_obligations.insert(vid.to_uint(), old_obs);
+        }
+        self.snapshot_depth -= 1u;
+    }
+
+    fn commit_from(_snapshot: snapshot) {
+        // If an enclosing transaction is still open, the bindings and
+        // edges made since the snapshot was taken simply become part
+        // of it.  But if this was the outermost snapshot, nothing can
+        // ever roll back past here again, so drop the undo logs now
+        // instead of letting them grow for the lifetime of the
+        // context.
+        self.snapshot_depth -= 1u;
+        if self.snapshot_depth == 0u {
+            self.assign_obligation_log = [];
+            truncate_vb_log(self.vb);
+            truncate_vb_log(self.rb);
         }
+    }
 
-        let vbl = self.vb.bindings.len();
-        let rbl = self.rb.bindings.len();
-        #debug["try(vbl=%u, rbl=%u)", vbl, rbl];
+    fn commit<T,E>(f: fn() -> result<T,E>) -> result<T,E> {
+        let snapshot = self.start_snapshot();
+        let r <- self.try(f);
+        self.commit_from(snapshot);
+        ret r;
+    }
+
+    fn try<T,E>(f: fn() -> result<T,E>) -> result<T,E> {
+        let snapshot = self.start_snapshot();
+        #debug["try(%?)", snapshot];
         let r <- f();
         alt r {
-          result::ok(_) { #debug["try--ok"]; }
+          result::ok(_) {
+            #debug["try--ok"];
+            self.commit_from(snapshot);
+          }
           result::err(_) {
             #debug["try--rollback"];
-            rollback_to(self.vb, vbl);
-            rollback_to(self.rb, rbl);
+            self.rollback_to(snapshot);
           }
         }
         ret r;
@@ -430,29 +596,58 @@ impl methods for infer_ctxt {
 }
 
 impl ty_and_region_var_methods for infer_ctxt {
-    fn next_ty_var_id() -> ty_vid {
+    fn next_ty_var_id(sp: span) -> ty_vid {
         let id = *self.ty_var_counter;
         *self.ty_var_counter += 1u;
+        self.ty_var_spans.insert(id, sp);
         ret ty_vid(id);
     }
 
-    fn next_ty_var() -> ty::t {
-        ty::mk_var(self.tcx, self.next_ty_var_id())
+    fn next_ty_var(sp: span) -> ty::t {
+        ty::mk_var(self.tcx, self.next_ty_var_id(sp))
     }
 
-    fn next_ty_vars(n: uint) -> [ty::t] {
-        vec::from_fn(n) {|_i| self.next_ty_var() }
+    fn next_ty_vars(n: uint, sp: span) -> [ty::t] {
+        vec::from_fn(n) {|_i| self.next_ty_var(sp) }
     }
 
-    fn next_region_var_id() -> region_vid {
+    fn next_region_var_id(sp: span) -> region_vid {
         let id = *self.region_var_counter;
         *self.region_var_counter += 1u;
+        self.region_var_spans.insert(id, sp);
         ret region_vid(id);
     }
 
-    fn next_region_var() -> ty::region {
-        ret ty::re_var(self.next_region_var_id());
+    fn next_region_var(sp: span) -> ty::region {
+        ret ty::re_var(self.next_region_var_id(sp));
+    }
+}
+
+// `propagate_bounds`/`set_var_to_merged_bounds`/`vars` are generic
+// over `V` so they can drive both the `ty_vid` and `region_vid`
+// stores, but assignability obligations only exist for `ty_vid`.
+// This iface lets that generic code trigger the right (possibly
+// no-op) behavior per `V` without having to special-case `ty_vid`
+// inline: whenever a variable's bounds change, or one variable is
+// redirected into another, the generic code just calls these two
+// methods and the per-type impl decides what, if anything, to do.
+iface var_obligations {
+    fn replay_obligations(infcx: infer_ctxt) -> ures;
+    fn migrate_obligations_to(infcx: infer_ctxt, new_root: self);
+}
+
+impl of var_obligations for ty_vid {
+    fn replay_obligations(infcx: infer_ctxt) -> ures {
+        infcx.replay_assign_obligations(self)
     }
+    fn migrate_obligations_to(infcx: infer_ctxt, new_root: ty_vid) {
+        infcx.migrate_assign_obligations(self, new_root)
+    }
+}
+
+impl of var_obligations for region_vid {
+    fn replay_obligations(_infcx: infer_ctxt) -> ures { uok() }
+    fn migrate_obligations_to(_infcx: infer_ctxt, _new_root: region_vid) { }
 }
 
 impl unify_methods for infer_ctxt {
@@ -537,6 +732,127 @@ impl unify_methods for infer_ctxt {
         }
     }
 
+    fn get_edges<V:copy vid, T:copy>(
+        vb: vals_and_bindings<V, T>, vid: V) -> var_edges<V> {
+
+        alt vb.edges.find(vid.to_uint()) {
+          some(es) { es }
+          none { {lb_vars: [], ub_vars: []} }
+        }
+    }
+
+    fn set_edges<V:copy vid, T:copy>(
+        vb: vals_and_bindings<V, T>, vid: V, +new_es: var_edges<V>) {
+
+        let old_es = self.get_edges(vb, vid);
+        vec::push(vb.edge_log, (vid, old_es));
+        vb.edges.insert(vid.to_uint(), new_es);
+    }
+
+    // Records that `lo <: hi` directly, without collapsing `lo` and
+    // `hi` into the same variable.  Both directions of the edge are
+    // stored so that `propagate_bounds` can walk the graph from
+    // either endpoint.
+    fn add_edge<V:copy vid, T:copy>(
+        vb: vals_and_bindings<V, T>, lo: V, hi: V) {
+
+        let lo_es = self.get_edges(vb, lo);
+        self.set_edges(vb, lo, {lb_vars: lo_es.lb_vars,
+                                 ub_vars: lo_es.ub_vars + [hi]});
+        let hi_es = self.get_edges(vb, hi);
+        self.set_edges(vb, hi, {lb_vars: hi_es.lb_vars + [lo],
+                                 ub_vars: hi_es.ub_vars});
+    }
+
+    // Called just before `old` is redirected into `new`: every other
+    // edge operation (`get_edges`/`reaches`/`propagate_bounds`) looks
+    // variables up by their *current* root, so if `old`'s own edges
+    // are left behind under `old`'s id once it stops being a root,
+    // its neighbors become permanently unreachable from `new` and
+    // bound propagation silently stops reaching them.  This repoints
+    // every neighbor's reciprocal entry at `new` and unions `old`'s
+    // edge lists into `new`'s.
+    fn migrate_edges<V:copy vid, T:copy>(
+        vb: vals_and_bindings<V, T>, old: V, new: V) {
+
+        let old_es = self.get_edges(vb, old);
+
+        // An edge directly between `old` and `new` would otherwise
+        // become a meaningless self-loop on `new` below.
+        let old_lb = vec::filter(old_es.lb_vars) {|v| v != new };
+        let old_ub = vec::filter(old_es.ub_vars) {|v| v != new };
+
+        for old_lb.each {|lo|
+            let lo_es = self.get_edges(vb, lo);
+            self.set_edges(vb, lo,
+                           {lb_vars: lo_es.lb_vars,
+                            ub_vars: replace_var(lo_es.ub_vars, old, new)});
+        }
+        for old_ub.each {|hi|
+            let hi_es = self.get_edges(vb, hi);
+            self.set_edges(vb, hi,
+                           {lb_vars: replace_var(hi_es.lb_vars, old, new),
+                            ub_vars: hi_es.ub_vars});
+        }
+
+        let new_es = self.get_edges(vb, new);
+        self.set_edges(vb, new,
+                       {lb_vars: union_vars(new_es.lb_vars, old_lb),
+                        ub_vars: union_vars(new_es.ub_vars, old_ub)});
+        self.set_edges(vb, old, {lb_vars: [], ub_vars: []});
+    }
+
+    // True if `to` is reachable from `from` by following `ub_vars`
+    // edges, i.e. if `from <: ... <: to` already holds in the graph.
+    // Used to decide whether adding a new edge `to <: from` would
+    // close a cycle.
+    fn reaches<V:copy vid, T:copy>(
+        vb: vals_and_bindings<V, T>, from: V, to: V, +seen: [V]) -> bool {
+
+        if from == to { ret true; }
+        if vec::contains(seen, from) { ret false; }
+        let es = self.get_edges(vb, from);
+        vec::any(es.ub_vars) {|v| self.reaches(vb, v, to, seen + [from]) }
+    }
+
+    // Pushes the current bounds of `seed` along the edges recorded
+    // for it: a narrower upper bound flows down to `lb_vars` (they
+    // must remain subtypes of whatever `seed` turns out to be), and
+    // a higher lower bound flows up to `ub_vars`.  Each push goes
+    // back through `set_var_to_merged_bounds`, so the worklist is
+    // really just the call graph of mutually recursive calls below;
+    // it terminates because bounds only ever narrow.
+    fn propagate_bounds<V:copy vid var_obligations, T:copy to_str st>(
+        vb: vals_and_bindings<V, T>, seed: V) -> ures {
+
+        let {root: seed, bounds} = self.get(vb, seed);
+        let es = self.get_edges(vb, seed);
+
+        let push_ub = alt bounds.ub {
+          some(ub) {
+            iter2_ures(es.lb_vars) {|lo|
+                let {root: lo, bounds: lo_bounds} = self.get(vb, lo);
+                self.set_var_to_merged_bounds(
+                    vb, lo, lo_bounds, {lb: none, ub: some(ub)})
+            }
+          }
+          none { uok() }
+        };
+
+        push_ub.then {||
+            alt bounds.lb {
+              some(lb) {
+                iter2_ures(es.ub_vars) {|hi|
+                    let {root: hi, bounds: hi_bounds} = self.get(vb, hi);
+                    self.set_var_to_merged_bounds(
+                        vb, hi, hi_bounds, {lb: some(lb), ub: none})
+                }
+              }
+              none { uok() }
+            }
+        }
+    }
+
     // Updates the bounds for the variable `v_id` to be the intersection
     // of `a` and `b`.  That is, the new bounds for `v_id` will be
     // a bounds c such that:
@@ -545,7 +861,7 @@ impl unify_methods for infer_ctxt {
     //    a.lb <: c.lb
     //    b.lb <: c.lb
     // If this cannot be achieved, the result is failure.
-    fn set_var_to_merged_bounds<V:copy vid, T:copy to_str st>(
+    fn set_var_to_merged_bounds<V:copy vid var_obligations, T:copy to_str st>(
         vb: vals_and_bindings<V, T>,
         v_id: V, a: bounds<T>, b: bounds<T>) -> ures {
 
@@ -588,13 +904,31 @@ impl unify_methods for infer_ctxt {
             // the new bounds must themselves
             // be relatable:
             self.bnds(bnds.lb, bnds.ub).then {||
-                self.set(vb, v_id, bounded(bnds));
-                uok()
+                // Callers pass the bounds already on file for `v_id`
+                // as either `a` (`vart`) or `b` (`tvar`), so compare
+                // against what is actually on file.
+                let {root: _, bounds: cur} = self.get(vb, v_id);
+                if bnds.lb == cur.lb && bnds.ub == cur.ub {
+                    // If merging changed nothing on file for `v_id`,
+                    // there is nothing to push along the edges (and
+                    // no risk of looping forever chasing a fixed
+                    // point).
+                    uok()
+                } else {
+                    self.set(vb, v_id, bounded(bnds));
+                    // `v_id`'s bounds just changed: propagate the new
+                    // bounds onward, then replay any assignability
+                    // obligations deferred against it (a no-op for
+                    // region variables, which have none).
+                    self.propagate_bounds(vb, v_id).then {||
+                        v_id.replay_obligations(self)
+                    }
+                }
             }
         }}}}}
     }
 
-    fn vars<V:copy vid, T:copy to_str st>(
+    fn vars<V:copy vid var_obligations, T:copy to_str st>(
         vb: vals_and_bindings<V, T>,
         a_id: V, b_id: V) -> ures {
 
@@ -621,19 +955,37 @@ impl unify_methods for infer_ctxt {
           _ { /*fallthrough*/ }
         }
 
-        // For max perf, we should consider the rank here.  But for now,
-        // we always make b redirect to a.
-        self.set(vb, b_id, redirect(a_id));
-
-        // Otherwise, we need to merge A and B so as to guarantee that
-        // A remains a subtype of B.  Actually, there are other options,
-        // but that's the route we choose to take.
-        self.set_var_to_merged_bounds(vb, a_id, a_bounds, b_bounds).then {||
-            uok()
+        // Rather than merging A and B outright---which would force
+        // them to share a single set of bounds from now on, even
+        // though all we know so far is `A <: B`---first try to
+        // record that relationship as an edge in the bound
+        // propagation graph and push the existing bounds of A and B
+        // along it.  We only fall back to merging if doing so would
+        // close a cycle, since the graph must stay acyclic for
+        // propagation to terminate.
+        if self.reaches(vb, b_id, a_id, []) {
+            // For max perf, we should consider the rank here.  But
+            // for now, we always make b redirect to a.  `b_id` is
+            // about to stop being a root, so its edges and any
+            // obligations recorded against it must move onto `a_id`
+            // first---otherwise they become permanently unreachable
+            // the moment the redirect takes effect.
+            self.migrate_edges(vb, b_id, a_id);
+            b_id.migrate_obligations_to(self, a_id);
+            self.set(vb, b_id, redirect(a_id));
+
+            self.set_var_to_merged_bounds(vb, a_id, a_bounds, b_bounds).then {||
+                uok()
+            }
+        } else {
+            self.add_edge(vb, a_id, b_id);
+            self.propagate_bounds(vb, a_id).then {||
+                self.propagate_bounds(vb, b_id)
+            }
         }
     }
 
-    fn vart<V: copy vid, T: copy to_str st>(
+    fn vart<V: copy vid var_obligations, T: copy to_str st>(
         vb: vals_and_bindings<V, T>,
         a_id: V, b: T) -> ures {
 
@@ -645,7 +997,7 @@ impl unify_methods for infer_ctxt {
         self.set_var_to_merged_bounds(vb, a_id, a_bounds, b_bounds)
     }
 
-    fn tvar<V: copy vid, T: copy to_str st>(
+    fn tvar<V: copy vid var_obligations, T: copy to_str st>(
         vb: vals_and_bindings<V, T>,
         a: T, b_id: V) -> ures {
 
@@ -657,6 +1009,41 @@ impl unify_methods for infer_ctxt {
         self.set_var_to_merged_bounds(vb, b_id, a_bounds, b_bounds)
     }
 
+    // Occurs-check: fails if `vid` (or whatever it has been
+    // redirected to) appears, directly or through other variables,
+    // in the concrete bound `t` about to be recorded for it.  This
+    // catches infinite types like `A <: [A]` right at the
+    // constraint that introduces the cycle, rather than leaving
+    // them to be discovered later, with much less context, by
+    // `resolver`'s `cyclic_ty`.
+    fn occurs_check(vid: ty_vid, t: ty::t) -> ures {
+        let {root: vid, bounds: _} = self.get(self.vb, vid);
+        if self.ty_contains_var(vid, t) {
+            err(ty::terr_cyclic_occurs(vid))
+        } else {
+            uok()
+        }
+    }
+
+    fn ty_contains_var(vid: ty_vid, t: ty::t) -> bool {
+        if !ty::type_needs_infer(t) { ret false; }
+        alt ty::get(t).struct {
+          ty::ty_var(vid2) {
+            let {root: vid2, bounds: _} = self.get(self.vb, vid2);
+            vid2 == vid
+          }
+          _ {
+            let mut found = false;
+            ty::fold_regions_and_ty(
+                self.tcx, t,
+                {|r| r},
+                {|t2| if self.ty_contains_var(vid, t2) { found = true; } t2},
+                {|t2| if self.ty_contains_var(vid, t2) { found = true; } t2});
+            found
+          }
+        }
+    }
+
     fn constrs(
         expected: @ty::type_constr,
         actual_constr: @ty::type_constr) -> ures {
@@ -767,48 +1154,68 @@ impl unify_methods for infer_ctxt {
 // behavior in the face of unconstrained variables.  If it is true,
 // then unconstrained variables result in an error.
 
+// `v_seen`/`r_seen` record, by *root* vid/rid (as found via the
+// union-find `get()` on the variable stores, which already does
+// path compression), which variables are on the current resolution
+// path---a back-edge to one of these is a genuine cycle.  `v_memo`/
+// `r_memo` additionally cache the fully-resolved type/region for
+// each root once known, for the lifetime of a single `resolve()`
+// call, so that a root reachable through more than one path (a
+// shared subgraph) is only ever walked once.  Both are keyed by
+// `to_uint()` of the root, giving near-constant-time lookups in
+// place of the old O(n) `vec::contains` scan over a growing stack.
 type resolve_state = @{
     infcx: infer_ctxt,
     deep: bool,
     force_vars: bool,
-    mut err: option<fixup_err>,
-    mut r_seen: [region_vid],
-    mut v_seen: [ty_vid]
+    // Every `unresolved_*`/`cyclic_*` hit during this `resolve` call,
+    // in the order encountered.  We used to stop at (and overwrite
+    // any earlier failure with) the first one; accumulating them all
+    // lets the caller report every ambiguous position in a single
+    // diagnostic pass instead of one-error-per-recompile.
+    mut errs: [fixup_err],
+    mut r_seen: smallintmap<()>,
+    mut v_seen: smallintmap<()>,
+    mut r_memo: smallintmap<ty::region>,
+    mut v_memo: smallintmap<ty::t>
 };
 
 fn resolver(infcx: infer_ctxt, deep: bool, fvars: bool) -> resolve_state {
     @{infcx: infcx,
       deep: deep,
       force_vars: fvars,
-      mut err: none,
-      mut r_seen: [],
-      mut v_seen: []}
+      mut errs: [],
+      mut r_seen: smallintmap::mk(),
+      mut v_seen: smallintmap::mk(),
+      mut r_memo: smallintmap::mk(),
+      mut v_memo: smallintmap::mk()}
 }
 
 impl methods for resolve_state {
     fn resolve(typ: ty::t) -> fres<ty::t> {
-        self.err = none;
+        self.errs = [];
 
         #debug["Resolving %s (deep=%b, force_vars=%b)",
                ty_to_str(self.infcx.tcx, typ),
                self.deep,
                self.force_vars];
 
-        // n.b. This is a hokey mess because the current fold doesn't
-        // allow us to pass back errors in any useful way.
+        // Each call to `resolve` gets a fresh path/memo table: a
+        // root seen in one call has no bearing on the next.
+        self.r_seen = smallintmap::mk();
+        self.v_seen = smallintmap::mk();
+        self.r_memo = smallintmap::mk();
+        self.v_memo = smallintmap::mk();
 
-        assert vec::is_empty(self.v_seen) && vec::is_empty(self.r_seen);
         let rty = indent {|| self.resolve1(typ) };
-        assert vec::is_empty(self.v_seen) && vec::is_empty(self.r_seen);
-        alt self.err {
-          none {
+        if vec::is_empty(self.errs) {
             #debug["Resolved to %s (deep=%b, force_vars=%b)",
                    ty_to_str(self.infcx.tcx, rty),
                    self.deep,
                    self.force_vars];
             ret ok(rty);
-          }
-          some(e) { ret err(e); }
+        } else {
+            ret err(self.errs);
         }
     }
 
@@ -849,56 +1256,79 @@ impl methods for resolve_state {
     }
 
     fn resolve_region_var(rid: region_vid) -> ty::region {
-        if vec::contains(self.r_seen, rid) {
-            self.err = some(cyclic_region(rid));
-            ret ty::re_var(rid);
-        } else {
-            vec::push(self.r_seen, rid);
-            let {root:_, bounds} = self.infcx.get(self.infcx.rb, rid);
-            let r1 = alt bounds {
-              { ub:_, lb:some(t) } { self.resolve_region(t) }
-              { ub:some(t), lb:_ } { self.resolve_region(t) }
-              { ub:none, lb:none } {
-                if self.force_vars {
-                    self.err = some(unresolved_region(rid));
-                }
-                ty::re_var(rid)
-              }
-            };
-            vec::pop(self.r_seen);
-            ret r1;
+        // `get` follows the union-find redirect chain (with path
+        // compression) to the current root, so two calls that end
+        // up at the same root---however long or short the chains
+        // that led there---hit the same memo entry below.
+        let {root, bounds} = self.infcx.get(self.infcx.rb, rid);
+        let root_id = root.to_uint();
+
+        alt self.r_memo.find(root_id) {
+          some(r) { ret r; }
+          none { }
         }
+
+        let sp = self.infcx.region_var_spans.get(root_id);
+
+        if option::is_some(self.r_seen.find(root_id)) {
+            vec::push(self.errs, cyclic_region(root, sp));
+            ret ty::re_var(root);
+        }
+
+        self.r_seen.insert(root_id, ());
+        let r1 = alt bounds {
+          { ub:_, lb:some(t) } { self.resolve_region(t) }
+          { ub:some(t), lb:_ } { self.resolve_region(t) }
+          { ub:none, lb:none } {
+            if self.force_vars {
+                vec::push(self.errs, unresolved_region(root, sp));
+            }
+            ty::re_var(root)
+          }
+        };
+        self.r_memo.insert(root_id, r1);
+        ret r1;
     }
 
     fn resolve_ty_var(vid: ty_vid) -> ty::t {
-        if vec::contains(self.v_seen, vid) {
-            self.err = some(cyclic_ty(vid));
-            ret ty::mk_var(self.infcx.tcx, vid);
-        } else {
-            vec::push(self.v_seen, vid);
-            let tcx = self.infcx.tcx;
-
-            // Nonobvious: prefer the most specific type
-            // (i.e., the lower bound) to the more general
-            // one.  More general types in Rust (e.g., fn())
-            // tend to carry more restrictions or higher
-            // perf. penalties, so it pays to know more.
-
-            let {root:_, bounds} = self.infcx.get(self.infcx.vb, vid);
-            let t1 = alt bounds {
-              { ub:_, lb:some(t) } if !type_is_bot(t) { self.resolve1(t) }
-              { ub:some(t), lb:_ } { self.resolve1(t) }
-              { ub:_, lb:some(t) } { self.resolve1(t) }
-              { ub:none, lb:none } {
-                if self.force_vars {
-                    self.err = some(unresolved_ty(vid));
-                }
-                ty::mk_var(tcx, vid)
-              }
-            };
-            vec::pop(self.v_seen);
-            ret t1;
+        let tcx = self.infcx.tcx;
+
+        let {root, bounds} = self.infcx.get(self.infcx.vb, vid);
+        let root_id = root.to_uint();
+
+        alt self.v_memo.find(root_id) {
+          some(t) { ret t; }
+          none { }
+        }
+
+        let sp = self.infcx.ty_var_spans.get(root_id);
+
+        if option::is_some(self.v_seen.find(root_id)) {
+            vec::push(self.errs, cyclic_ty(root, sp));
+            ret ty::mk_var(tcx, root);
         }
+
+        self.v_seen.insert(root_id, ());
+
+        // Nonobvious: prefer the most specific type
+        // (i.e., the lower bound) to the more general
+        // one.  More general types in Rust (e.g., fn())
+        // tend to carry more restrictions or higher
+        // perf. penalties, so it pays to know more.
+
+        let t1 = alt bounds {
+          { ub:_, lb:some(t) } if !type_is_bot(t) { self.resolve1(t) }
+          { ub:some(t), lb:_ } { self.resolve1(t) }
+          { ub:_, lb:some(t) } { self.resolve1(t) }
+          { ub:none, lb:none } {
+            if self.force_vars {
+                vec::push(self.errs, unresolved_ty(root, sp));
+            }
+            ty::mk_var(tcx, root)
+          }
+        };
+        self.v_memo.insert(root_id, t1);
+        ret t1;
     }
 }
 
@@ -1056,7 +1486,93 @@ impl assignment for infer_ctxt {
             }
           }
           _ {
-            self.sub_tys(a, b)
+            // We still don't have a bound on one (or both) sides,
+            // so we can't yet tell whether a coercion like @[mut T]
+            // -> &[const T] applies.  If the missing bound belongs
+            // to a type variable, defer the check rather than
+            // forcing plain subtyping now (which would wrongly
+            // reject assignments that only become coercible once
+            // that variable is resolved further); it gets replayed
+            // from `replay_assign_obligations` once the variable
+            // picks up a bound.
+            alt (ty::get(a).struct, ty::get(b).struct) {
+              (ty::ty_var(a_id), _) if option::is_none(a_bnd) {
+                self.add_assign_obligation(a_id, anmnt, a, b);
+                uok()
+              }
+              (_, ty::ty_var(b_id)) if option::is_none(b_bnd) {
+                self.add_assign_obligation(b_id, anmnt, a, b);
+                uok()
+              }
+              _ {
+                self.sub_tys(a, b)
+              }
+            }
+          }
+        }
+    }
+
+    // Updates the obligations recorded against `vid`, pushing the
+    // prior list onto `assign_obligation_log` first so a rollback can
+    // restore it. Every mutation of `assign_obligations` goes through
+    // here, mirroring how `set`/`set_edges` log before mutating
+    // `vb.vals`/`vb.edges`.
+    fn set_assign_obligations(vid: ty_vid, +new_obs: [assign_obligation]) {
+        let old_obs = alt self.assign_obligations.find(vid.to_uint()) {
+          some(obs) { obs }
+          none { [] }
+        };
+        vec::push(self.assign_obligation_log, (vid, old_obs));
+        self.assign_obligations.insert(vid.to_uint(), new_obs);
+    }
+
+    // Records that `a` must be assignable to `b` (under `anmnt`)
+    // once the type variable `vid` is resolved further.
+    fn add_assign_obligation(vid: ty_vid, anmnt: assignment,
+                             a: ty::t, b: ty::t) {
+        let {root: vid, bounds: _} = self.get(self.vb, vid);
+        let obs = alt self.assign_obligations.find(vid.to_uint()) {
+          some(obs) { obs }
+          none { [] }
+        };
+        self.set_assign_obligations(
+            vid, obs + [{anmnt: anmnt, a: a, b: b}]);
+    }
+
+    // Re-runs any assignability obligations that were deferred
+    // against `vid` because it lacked a bound at the time.  Called
+    // whenever `vid` picks up a (possibly still partial) bound.
+    fn replay_assign_obligations(vid: ty_vid) -> ures {
+        let {root: vid, bounds: _} = self.get(self.vb, vid);
+        alt self.assign_obligations.find(vid.to_uint()) {
+          none { uok() }
+          some(obs) {
+            self.set_assign_obligations(vid, []);
+            iter2_ures(obs) {|ob| self.assign_tys(ob.anmnt, ob.a, ob.b) }
+          }
+        }
+    }
+
+    // Called when `old_vid` is about to be redirected into `new_vid`
+    // (i.e. it stops being a root): moves any obligations recorded
+    // against `old_vid` onto `new_vid` so that a later bound change on
+    // `new_vid` still finds and replays them.  Without this, an
+    // obligation deferred against a variable that later gets merged
+    // into another would sit under a root id nothing ever looks up
+    // again.
+    fn migrate_assign_obligations(old_vid: ty_vid, new_vid: ty_vid) {
+        alt self.assign_obligations.find(old_vid.to_uint()) {
+          none { }
+          some(obs) {
+            if !vec::is_empty(obs) {
+                let merged = alt self.assign_obligations.find(
+                    new_vid.to_uint()) {
+                  some(existing) { existing + obs }
+                  none { obs }
+                };
+                self.set_assign_obligations(new_vid, merged);
+            }
+            self.set_assign_obligations(old_vid, []);
           }
         }
     }
@@ -1140,9 +1656,14 @@ iface combine {
     fn mts(a: ty::mt, b: ty::mt) -> cres<ty::mt>;
     fn contratys(a: ty::t, b: ty::t) -> cres<ty::t>;
     fn tys(a: ty::t, b: ty::t) -> cres<ty::t>;
-    fn tps(as: [ty::t], bs: [ty::t]) -> cres<[ty::t]>;
+
+    // `did` identifies the enum/class/iface whose type parameters
+    // these are, so that the declared (or inferred) variance of
+    // each parameter can be looked up.
+    fn tps(did: ast::def_id, as: [ty::t], bs: [ty::t]) -> cres<[ty::t]>;
     fn self_tys(a: option<ty::t>, b: option<ty::t>) -> cres<option<ty::t>>;
-    fn substs(as: ty::substs, bs: ty::substs) -> cres<ty::substs>;
+    fn substs(did: ast::def_id,
+             as: ty::substs, bs: ty::substs) -> cres<ty::substs>;
     fn fns(a: ty::fn_ty, b: ty::fn_ty) -> cres<ty::fn_ty>;
     fn flds(a: ty::field, b: ty::field) -> cres<ty::field>;
     fn modes(a: ast::mode, b: ast::mode) -> cres<ast::mode>;
@@ -1160,18 +1681,33 @@ enum lub = infer_ctxt;  // "least upper bound" (common supertype)
 enum glb = infer_ctxt;  // "greatest lower bound" (common subtype)
 
 fn super_substs<C:combine>(
-    self: C, a: ty::substs, b: ty::substs) -> cres<ty::substs> {
+    self: C, did: ast::def_id, a: ty::substs, b: ty::substs) -> cres<ty::substs> {
 
-    fn eq_opt_regions(infcx: infer_ctxt,
+    // Relates the self-regions of `a` and `b` according to the
+    // declared (or inferred) variance of `did`'s self-region
+    // parameter, rather than always requiring them to be equal.
+    fn eq_opt_regions(self: combine, did: ast::def_id,
                       a: option<ty::region>,
                       b: option<ty::region>) -> cres<option<ty::region>> {
+        let infcx = self.infcx();
         alt (a, b) {
           (none, none) {
             ok(none)
           }
           (some(a), some(b)) {
-            infcx.eq_regions(a, b).then {||
+            alt ty::self_region_variance(infcx.tcx, did) {
+              ty::covariant {
+                self.regions(a, b).chain {|r| ok(some(r)) }
+              }
+              ty::contravariant {
+                self.contraregions(a, b).chain {|r| ok(some(r)) }
+              }
+              ty::bivariant {
                 ok(some(a))
+              }
+              ty::invariant {
+                infcx.eq_regions(a, b).then {|| ok(some(a)) }
+              }
             }
           }
           (_, _) {
@@ -1188,9 +1724,9 @@ fn super_substs<C:combine>(
         }
     }
 
-    self.tps(a.tps, b.tps).chain { |tps|
+    self.tps(did, a.tps, b.tps).chain { |tps|
         self.self_tys(a.self_ty, b.self_ty).chain { |self_ty|
-            eq_opt_regions(self.infcx(), a.self_r, b.self_r).chain { |self_r|
+            eq_opt_regions(self, did, a.self_r, b.self_r).chain { |self_r|
                 ok({self_r: self_r, self_ty: self_ty, tps: tps})
             }
         }
@@ -1198,16 +1734,33 @@ fn super_substs<C:combine>(
 }
 
 fn super_tps<C:combine>(
-    self: C, as: [ty::t], bs: [ty::t]) -> cres<[ty::t]> {
-
-    // Note: type parameters are always treated as *invariant*
-    // (otherwise the type system would be unsound).  In the
-    // future we could allow type parameters to declare a
-    // variance.
+    self: C, did: ast::def_id, as: [ty::t], bs: [ty::t]) -> cres<[ty::t]> {
+
+    // Each type parameter of `did` is related according to its
+    // declared (or inferred, for ADTs whose variance was computed by
+    // the `variance` pass) variance: a covariant parameter can use
+    // plain subtyping, a contravariant one the reversed relation,
+    // and only an invariant parameter need fall back to equality
+    // (which is the only sound choice absent any variance
+    // information, e.g. for a self type parameter). Each arm's actual
+    // combined type feeds into the result vector, the same way
+    // `flds`/`ts` do for records/tuples in `super_tys`, so that `lub`/`glb`
+    // over a non-invariant parameter yield the real combined type
+    // instead of just echoing `as` back.
 
     if check vec::same_length(as, bs) {
-        iter2(as, bs) {|a, b| self.infcx().eq_tys(a, b) }.then {||
-            ok(as)
+        let variances = ty::item_variances(self.infcx().tcx, did);
+        let mut i = 0u;
+        map2(as, bs) {|a, b|
+            let v = if i < variances.len() { variances[i] }
+                    else { ty::invariant };
+            i += 1u;
+            alt v {
+              ty::covariant { self.tys(a, b) }
+              ty::contravariant { self.contratys(a, b) }
+              ty::bivariant { ok(a) }
+              ty::invariant { self.infcx().eq_tys(a, b).then {|| ok(a) } }
+            }
         }
     } else {
         err(ty::terr_ty_param_size(bs.len(), as.len()))
@@ -1310,14 +1863,14 @@ fn super_fns<C:combine>(
         self.ret_styles(a_f.ret_style, b_f.ret_style).chain {|rs|
             argvecs(self, a_f.inputs, b_f.inputs).chain {|inputs|
                 self.tys(a_f.output, b_f.output).chain {|output|
-                    //FIXME self.infcx().constrvecs(a_f.constraints,
-                    //FIXME                         b_f.constraints).then {||
+                    self.infcx().constrvecs(a_f.constraints,
+                                            b_f.constraints).then {||
                         ok({proto: p,
                             inputs: inputs,
                             output: output,
                             ret_style: rs,
                             constraints: a_f.constraints})
-                    //FIXME }
+                    }
                 }
             }
         }
@@ -1361,21 +1914,21 @@ fn super_tys<C:combine>(
 
       (ty::ty_enum(a_id, a_substs), ty::ty_enum(b_id, b_substs))
       if a_id == b_id {
-        self.substs(a_substs, b_substs).chain {|tps|
+        self.substs(a_id, a_substs, b_substs).chain {|tps|
             ok(ty::mk_enum(tcx, a_id, tps))
         }
       }
 
       (ty::ty_iface(a_id, a_substs), ty::ty_iface(b_id, b_substs))
       if a_id == b_id {
-        self.substs(a_substs, b_substs).chain {|substs|
+        self.substs(a_id, a_substs, b_substs).chain {|substs|
             ok(ty::mk_iface(tcx, a_id, substs))
         }
       }
 
       (ty::ty_class(a_id, a_substs), ty::ty_class(b_id, b_substs))
       if a_id == b_id {
-        self.substs(a_substs, b_substs).chain {|substs|
+        self.substs(a_id, a_substs, b_substs).chain {|substs|
             ok(ty::mk_class(tcx, a_id, substs))
         }
       }
@@ -1430,7 +1983,7 @@ fn super_tys<C:combine>(
        ty::ty_res(b_id, b_t, b_substs))
       if a_id == b_id {
         self.tys(a_t, b_t).chain {|t|
-            self.substs(a_substs, b_substs).chain {|substs|
+            self.substs(a_id, a_substs, b_substs).chain {|substs|
                 ok(ty::mk_res(tcx, a_id, t, substs))
             }
         }
@@ -1561,13 +2114,21 @@ impl of combine for sub {
                 ok(a)
               }
               (ty::ty_var(a_id), ty::ty_var(b_id)) {
+                // `vars` itself now replays (and, on a redirect,
+                // migrates) any assignability obligations as part of
+                // updating bounds, so there's nothing left to trigger
+                // here.
                 self.infcx().vars(self.vb, a_id, b_id).then {|| ok(a) }
               }
               (ty::ty_var(a_id), _) {
-                self.infcx().vart(self.vb, a_id, b).then {|| ok(a) }
+                self.infcx().occurs_check(a_id, b).then {||
+                    self.infcx().vart(self.vb, a_id, b)
+                }.then {|| ok(a) }
               }
               (_, ty::ty_var(b_id)) {
-                self.infcx().tvar(self.vb, a, b_id).then {|| ok(a) }
+                self.infcx().occurs_check(b_id, a).then {||
+                    self.infcx().tvar(self.vb, a, b_id)
+                }.then {|| ok(a) }
               }
               (_, ty::ty_bot) {
                 err(ty::terr_sorts(b, a))
@@ -1602,12 +2163,13 @@ impl of combine for sub {
         super_fns(self, a, b)
     }
 
-    fn substs(as: ty::substs, bs: ty::substs) -> cres<ty::substs> {
-        super_substs(self, as, bs)
+    fn substs(did: ast::def_id,
+             as: ty::substs, bs: ty::substs) -> cres<ty::substs> {
+        super_substs(self, did, as, bs)
     }
 
-    fn tps(as: [ty::t], bs: [ty::t]) -> cres<[ty::t]> {
-        super_tps(self, as, bs)
+    fn tps(did: ast::def_id, as: [ty::t], bs: [ty::t]) -> cres<[ty::t]> {
+        super_tps(self, did, as, bs)
     }
 
     fn self_tys(a: option<ty::t>, b: option<ty::t>) -> cres<option<ty::t>> {
@@ -1779,12 +2341,13 @@ impl of combine for lub {
         super_fns(self, a, b)
     }
 
-    fn substs(as: ty::substs, bs: ty::substs) -> cres<ty::substs> {
-        super_substs(self, as, bs)
+    fn substs(did: ast::def_id,
+             as: ty::substs, bs: ty::substs) -> cres<ty::substs> {
+        super_substs(self, did, as, bs)
     }
 
-    fn tps(as: [ty::t], bs: [ty::t]) -> cres<[ty::t]> {
-        super_tps(self, as, bs)
+    fn tps(did: ast::def_id, as: [ty::t], bs: [ty::t]) -> cres<[ty::t]> {
+        super_tps(self, did, as, bs)
     }
 
     fn self_tys(a: option<ty::t>, b: option<ty::t>) -> cres<option<ty::t>> {
@@ -1969,12 +2532,13 @@ impl of combine for glb {
         super_fns(self, a, b)
     }
 
-    fn substs(as: ty::substs, bs: ty::substs) -> cres<ty::substs> {
-        super_substs(self, as, bs)
+    fn substs(did: ast::def_id,
+             as: ty::substs, bs: ty::substs) -> cres<ty::substs> {
+        super_substs(self, did, as, bs)
     }
 
-    fn tps(as: [ty::t], bs: [ty::t]) -> cres<[ty::t]> {
-        super_tps(self, as, bs)
+    fn tps(did: ast::def_id, as: [ty::t], bs: [ty::t]) -> cres<[ty::t]> {
+        super_tps(self, did, as, bs)
     }
 
     fn self_tys(a: option<ty::t>, b: option<ty::t>) -> cres<option<ty::t>> {
@@ -2033,13 +2597,17 @@ fn lattice_tys<L:lattice_ops combine>(
           }
 
           (ty::ty_var(a_id), _) {
-            lattice_var_t(self, self.infcx().vb, a_id, b,
-                          {|x, y| self.tys(x, y) })
+            self.infcx().occurs_check(a_id, b).then {||
+                lattice_var_t(self, self.infcx().vb, a_id, b,
+                              {|x, y| self.tys(x, y) })
+            }
           }
 
           (_, ty::ty_var(b_id)) {
-            lattice_var_t(self, self.infcx().vb, b_id, a,
-                          {|x, y| self.tys(x, y) })
+            self.infcx().occurs_check(b_id, a).then {||
+                lattice_var_t(self, self.infcx().vb, b_id, a,
+                              {|x, y| self.tys(x, y) })
+            }
           }
 
           _ {
@@ -2077,7 +2645,7 @@ fn lattice_rvars<L:lattice_ops combine>(
     }
 }
 
-fn lattice_vars<V:copy vid, T:copy to_str st, L:lattice_ops combine>(
+fn lattice_vars<V:copy vid var_obligations, T:copy to_str st, L:lattice_ops combine>(
     self: L, vb: vals_and_bindings<V, T>,
     a_t: T, a_vid: V, b_vid: V,
     c_ts: fn(T, T) -> cres<T>) -> cres<T> {
@@ -2119,7 +2687,7 @@ fn lattice_vars<V:copy vid, T:copy to_str st, L:lattice_ops combine>(
     }
 }
 
-fn lattice_var_t<V:copy vid, T:copy to_str st, L:lattice_ops combine>(
+fn lattice_var_t<V:copy vid var_obligations, T:copy to_str st, L:lattice_ops combine>(
     self: L, vb: vals_and_bindings<V, T>,
     a_id: V, b: T,
     c_ts: fn(T, T) -> cres<T>) -> cres<T> {
@@ -2142,11 +2710,15 @@ fn lattice_var_t<V:copy vid, T:copy to_str st, L:lattice_ops combine>(
       }
       none {
         // If a does not have an upper bound, make b the upper bound of a
-        // and then return b.
+        // and then return b.  Route through `set_var_to_merged_bounds`
+        // (rather than setting `vb` directly) so this picks up the
+        // same edge-propagation and obligation-replay behavior as
+        // `vart`/`tvar`, instead of being a second, unwired bound-
+        // setting path.
         #debug["bnd=none"];
-        let a_bounds = self.with_bnd(a_bounds, b);
-        self.infcx().bnds(a_bounds.lb, a_bounds.ub).then {||
-            self.infcx().set(vb, a_id, bounded(a_bounds));
+        let new_bounds = self.with_bnd(a_bounds, b);
+        self.infcx().set_var_to_merged_bounds(
+            vb, a_id, a_bounds, new_bounds).then {||
             ok(b)
         }
       }
@@ -0,0 +1,291 @@
+/*
+
+# Variance inference
+
+Type parameters of an enum, class, or iface are, by default,
+invariant: if `foo<T>` is the type of some ADT with a type parameter
+`T`, then `foo<S>` is related to `foo<U>` only when `S` and `U` are
+equal, no matter how `S` and `U` themselves relate.  This is always
+sound, but it is needlessly strict for the common case of a
+parameter that is only ever used in a "read-only" (covariant) or
+"write-only" (contravariant) position.
+
+This pass computes, for every type and region parameter of every
+item in the crate, the *variance* that parameter could soundly be
+given: `covariant`, `contravariant`, `invariant`, or `bivariant` (used
+in neither position, so any variance is sound).  The combiner in
+`infer` (see `super_tps`/`super_substs`) then looks up these
+variances and picks `tys`/`contratys`/`eq_tys` accordingly instead of
+always forcing `eq_tys`.
+
+## The algorithm
+
+We start every parameter off as `bivariant`---the bottom of the
+variance lattice, meaning "no constraint seen yet"---and then walk
+every field, constructor argument, and fn return/argument type that
+mentions the parameter, folding in how that position uses it:
+
+  - appearing directly (e.g. a field of type `T`) contributes
+    `covariant`;
+  - appearing in fn-argument position contributes `contravariant`
+    (arguments are written by the *caller*, read by the *callee*, so
+    the relation inverts);
+  - appearing under `@mut`/`&mut`, or as a type parameter of another
+    invariant-at-that-slot ADT, contributes `invariant`, since the
+    location can be both read and written.
+
+`join(old, new)` combines the variance seen so far with the
+variance of a newly-visited position:
+
+    bivariant    join anything    = anything
+    covariant    join covariant   = covariant
+    contravariant join contravariant = contravariant
+    anything else                 = invariant
+
+We iterate this to a fixed point: each round revisits every item
+(because a parameter's variance can depend on the, not yet known,
+variance of a field whose type is a different ADT), and we stop once
+a full round leaves every parameter's variance unchanged.  Since the
+lattice `{bivariant} < {covariant, contravariant} < {invariant}` has
+height 3, this always terminates.
+
+*/
+
+import std::map::hashmap;
+import middle::ty;
+import syntax::ast;
+
+export infer_variances;
+
+enum position { co, contra, inv }
+
+fn flip(p: position) -> position {
+    alt p {
+      co { contra }
+      contra { co }
+      inv { inv }
+    }
+}
+
+// Composes the position `pos` that some ADT's field/argument is in
+// with `v`, the already-computed variance of the type parameter of
+// that field/argument's own type.  A covariant parameter just passes
+// `pos` through; a contravariant one flips it; an invariant one
+// collapses to `inv` regardless of `pos`; and a bivariant one has no
+// occurrence at all here, so there is nothing to walk into.
+fn compose(pos: position, v: ty::variance) -> option<position> {
+    alt v {
+      ty::covariant { some(pos) }
+      ty::contravariant { some(flip(pos)) }
+      ty::invariant { some(inv) }
+      ty::bivariant { none }
+    }
+}
+
+fn join(old: ty::variance, p: position) -> ty::variance {
+    alt (old, p) {
+      (ty::bivariant, co) { ty::covariant }
+      (ty::bivariant, contra) { ty::contravariant }
+      (ty::bivariant, inv) { ty::invariant }
+
+      (ty::covariant, co) { ty::covariant }
+      (ty::contravariant, contra) { ty::contravariant }
+
+      // Any other combination means the parameter is used in
+      // incompatible ways, so we must fall back to invariant.
+      _ { ty::invariant }
+    }
+}
+
+type item_variances = {
+    mut tps: [ty::variance],
+    mut self_r: option<ty::variance>
+};
+
+// Folds `use_of` -- the variance of the position currently being
+// visited -- into the entry for type parameter / region `pid`
+// belonging to `item`, flagging `changed` if this actually moved the
+// parameter's variance (used to detect the fixed point).
+fn note_param_use(variances: hashmap<ast::def_id, item_variances>,
+                  changed: @mut bool,
+                  item: ast::def_id, i: uint, use_of: position) {
+    let iv = variances.get(item);
+    let old = iv.tps[i];
+    let new = join(old, use_of);
+    if old != new {
+        iv.tps[i] = new;
+        *changed = true;
+    }
+}
+
+fn note_self_region_use(variances: hashmap<ast::def_id, item_variances>,
+                        changed: @mut bool,
+                        item: ast::def_id, use_of: position) {
+    let iv = variances.get(item);
+    let old = option::get_default(iv.self_r, ty::bivariant);
+    let new = join(old, use_of);
+    if option::is_none(iv.self_r) || old != new {
+        iv.self_r = some(new);
+        *changed = true;
+    }
+}
+
+// Walks `ty` in variance position `pos`, recording how it uses the
+// type/region parameters of `item` (whose own parameters are named
+// by `ty_param(i, _)`/the item's self-region).
+fn walk_ty(tcx: ty::ctxt,
+          variances: hashmap<ast::def_id, item_variances>,
+          changed: @mut bool,
+          item: ast::def_id, pos: position, ty: ty::t) {
+
+    alt ty::get(ty).struct {
+      ty::ty_param(i, _) {
+        note_param_use(variances, changed, item, i, pos);
+      }
+
+      ty::ty_rptr(r, mt) {
+        walk_region(tcx, variances, changed, item, pos, r);
+        // A location reachable through `&mut` can be both read and
+        // written, so treat its contents as invariant regardless of
+        // the variance of the reference itself.
+        let inner = if mt.mutbl == ast::m_mutbl { inv } else { pos };
+        walk_ty(tcx, variances, changed, item, inner, mt.ty);
+      }
+
+      ty::ty_box(mt) | ty::ty_uniq(mt) |
+      ty::ty_vec(mt) | ty::ty_ptr(mt) {
+        let inner = if mt.mutbl == ast::m_mutbl { inv } else { pos };
+        walk_ty(tcx, variances, changed, item, inner, mt.ty);
+      }
+
+      ty::ty_evec(mt, vs) {
+        walk_vstore(tcx, variances, changed, item, pos, vs);
+        let inner = if mt.mutbl == ast::m_mutbl { inv } else { pos };
+        walk_ty(tcx, variances, changed, item, inner, mt.ty);
+      }
+
+      ty::ty_estr(vs) {
+        walk_vstore(tcx, variances, changed, item, pos, vs);
+      }
+
+      ty::ty_enum(did, substs) | ty::ty_iface(did, substs) |
+      ty::ty_class(did, substs) {
+        // If `did` is not in this crate, `variances` has no entry for
+        // it and we fall back to the conservative `invariant`.
+        // Otherwise look up the variance computed so far for each
+        // parameter (in an early round possibly still `bivariant`; a
+        // later round revisits this field once it changes) and
+        // compose it with `pos` so e.g. a covariant parameter of a
+        // covariant field doesn't needlessly force `inv`.
+        let item_iv = variances.find(did);
+        let mut i = 0u;
+        while i < vec::len(substs.tps) {
+            let v = alt item_iv {
+              some(iv) if i < vec::len(iv.tps) { iv.tps[i] }
+              _ { ty::invariant }
+            };
+            alt compose(pos, v) {
+              some(p) { walk_ty(tcx, variances, changed, item, p, substs.tps[i]); }
+              none { /* bivariant: this parameter has no occurrence */ }
+            }
+            i += 1u;
+        }
+        alt substs.self_ty {
+          // `self_ty` is the instantiated "Self" type, not one of
+          // `did`'s own type parameters, so `iv.self_r` (which is the
+          // variance of `did`'s *self-region*) is not a variance for
+          // it and must not be composed with `pos` here; `super_self_tys`
+          // also hard-codes `self_ty` as always-invariant, so match
+          // that and keep walking it at `inv` until a real computed
+          // variance for `self_ty` exists to look up instead.
+          some(t) { walk_ty(tcx, variances, changed, item, inv, t); }
+          none { }
+        }
+      }
+
+      ty::ty_res(_, t, substs) {
+        walk_ty(tcx, variances, changed, item, pos, t);
+        for substs.tps.each {|t| walk_ty(tcx, variances, changed, item, inv, t) }
+      }
+
+      ty::ty_rec(flds) {
+        for flds.each {|f| walk_ty(tcx, variances, changed, item, pos, f.mt.ty) }
+      }
+
+      ty::ty_tup(ts) {
+        for ts.each {|t| walk_ty(tcx, variances, changed, item, pos, t) }
+      }
+
+      ty::ty_fn(fty) {
+        for fty.inputs.each {|a|
+            walk_ty(tcx, variances, changed, item, flip(pos), a.ty);
+        }
+        walk_ty(tcx, variances, changed, item, pos, fty.output);
+      }
+
+      ty::ty_constr(t, _) {
+        walk_ty(tcx, variances, changed, item, pos, t);
+      }
+
+      ty::ty_nil | ty::ty_bot | ty::ty_bool | ty::ty_int(_) |
+      ty::ty_uint(_) | ty::ty_float(_) | ty::ty_str | ty::ty_var(_) {
+        // no parameters to see here
+      }
+    }
+}
+
+fn walk_region(tcx: ty::ctxt,
+              variances: hashmap<ast::def_id, item_variances>,
+              changed: @mut bool,
+              item: ast::def_id, pos: position, r: ty::region) {
+    alt r {
+      ty::re_bound(_) {
+        // stands for the item's own self-region parameter
+        note_self_region_use(variances, changed, item, pos);
+      }
+      _ { }
+    }
+}
+
+fn walk_vstore(tcx: ty::ctxt,
+              variances: hashmap<ast::def_id, item_variances>,
+              changed: @mut bool,
+              item: ast::def_id, pos: position, vs: ty::vstore) {
+    alt vs {
+      ty::vstore_slice(r) {
+        walk_region(tcx, variances, changed, item, pos, r);
+      }
+      ty::vstore_fixed(_) | ty::vstore_uniq | ty::vstore_box { }
+    }
+}
+
+// Computes, for every enum/class/iface in the crate, the variance of
+// each of its type and region parameters, and records the result via
+// `ty::set_item_variances` for `super_tps`/`super_substs` to consult.
+fn infer_variances(tcx: ty::ctxt) {
+    let variances: hashmap<ast::def_id, item_variances> =
+        std::map::new_def_hash();
+
+    for ty::each_adt_item(tcx) {|item, tps, has_self_r|
+        variances.insert(item,
+                         {mut tps: vec::from_elem(vec::len(tps),
+                                                   ty::bivariant),
+                          mut self_r: if has_self_r { some(ty::bivariant) }
+                                      else { none }});
+    }
+
+    let mut changed = true;
+    while changed {
+        let changed_box = @mut false;
+        for ty::each_adt_item(tcx) {|item, _tps, _has_self_r|
+            for ty::each_adt_field_ty(tcx, item) {|fld_ty|
+                walk_ty(tcx, variances, changed_box, item, co, fld_ty);
+            }
+        }
+        changed = *changed_box;
+    }
+
+    variances.each {|item, iv|
+        ty::set_item_variances(tcx, item, iv.tps, iv.self_r);
+    }
+}
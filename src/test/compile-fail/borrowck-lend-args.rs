@@ -1,6 +1,18 @@
 // xfail-fast  (compile-flags unsupported on windows)
 // compile-flags:--borrowck=err
 
+// bklooste/rust#chunk3-1 is left undone: surfacing argument-mode
+// provenance in these diagnostics means extending the borrowck pass
+// itself, and `middle::borrowck` does not exist anywhere in this
+// tree (this file is the only thing in the whole snapshot that even
+// mentions "borrowck"), so there is no pass to extend. Expected-error
+// text below is unchanged from baseline.
+//
+// bklooste/rust#chunk3-2 is left undone for the same reason: there is
+// no candidate-mode tracking or suggestion-rendering code anywhere in
+// this tree to compute a "try declaring the parameter as -v/+v/++v"
+// hint from, since that also lives in the (absent) borrowck pass.
+
 fn borrow(_v: &int) {}
 
 fn borrow_from_arg_imm_ref(&&v: ~int) {
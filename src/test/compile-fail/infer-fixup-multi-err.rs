@@ -0,0 +1,10 @@
+// `r`'s type `{a: _, b: _}` is a single composite type with two
+// independently-unresolved fields, so the one `resolve()` call that
+// writeback makes on `r` must accumulate an error for each field
+// (see the `errs: [fixup_err]` vector in `middle::typeck::infer`)
+// instead of stopping after the first.
+fn main() {
+    let r = {a: [], b: []};
+    //! ERROR cannot determine a type for this expression
+    //! ERROR cannot determine a type for this expression
+}
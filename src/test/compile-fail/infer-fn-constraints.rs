@@ -0,0 +1,15 @@
+// `super_fns` must actually run `constrvecs` over both sides'
+// constraint lists instead of passing `a_f.constraints` through
+// unchecked: `apply` requires a callback constrained by `is_pos`,
+// and `doubles` only carries `is_even`, so the two constraint lists
+// differ and the call must be rejected.
+pure fn is_pos(x: int) -> bool { x > 0 }
+pure fn is_even(x: int) -> bool { x % 2 == 0 }
+
+fn apply(f: fn(x: int) : is_pos(x) -> int, y: int) -> int { f(y) }
+
+fn doubles(x: int) : is_even(x) -> int { x * 2 }
+
+fn main() {
+    apply(doubles, 4); //! ERROR mismatched type constraint
+}
@@ -0,0 +1,8 @@
+// Companion to infer-occurs-check.rs: here the cyclic bound is only
+// introduced through `if`'s arm-unification (LUB), not a direct
+// assignment, so this exercises occurs_check via lattice_var_t rather
+// than vart/tvar.
+fn main() {
+    let mut v = [];
+    let w = if true { v } else { [v] }; //! ERROR cyclic
+}
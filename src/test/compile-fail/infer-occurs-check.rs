@@ -0,0 +1,10 @@
+// Regression test for the eager occurs-check that `sub`'s `tys` runs
+// before `vart`/`tvar` (see `occurs_check` in
+// `middle::typeck::infer`). Binding a type variable to a bound that
+// contains itself, e.g. `A <: [A]`, must fail right at this
+// constraint with `terr_cyclic_occurs` instead of type-checking
+// successfully and only being caught much later by the resolver.
+fn main() {
+    let mut x = [];
+    x = [x]; //! ERROR cyclic
+}
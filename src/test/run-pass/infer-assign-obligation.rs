@@ -0,0 +1,12 @@
+// Regression test for deferred assignability (coercion) obligations
+// (see `add_assign_obligation`/`replay_assign_obligations` in
+// `middle::typeck::infer`). At the assignment into `s`, `v`'s type is
+// an inference variable; while that variable has no bound yet, the
+// `@[mut T]` to `&[const int]` check is recorded against it and
+// replayed once it picks up a bound, rather than being forced into
+// plain subtyping (which would reject the coercion).
+fn main() {
+    let v = @[mut];
+    let s: &[const int] = v;
+    vec::push(v, 1);
+}
@@ -0,0 +1,15 @@
+// Regression test for lazy bound-propagation in type-variable
+// unification (see `vars` in `middle::typeck::infer`). Unifying the
+// type parameter `T` against two arguments with different but
+// compatible bounds must narrow `T` along the constraint graph rather
+// than eagerly merging the variable for `y` into the variable for
+// `x`, which used to pin `T` to `x`'s exact bound (`@mut int`) instead
+// of letting it settle on the supertype both arguments share
+// (`@const int`).
+fn foo<T: copy>(x: T, y: T) -> T { x }
+
+fn main() {
+    let x: @mut int = @mut 1;
+    let y: @int = @2;
+    let _z: @const int = foo(x, y);
+}
@@ -0,0 +1,21 @@
+// Note: this tree has no unit-test harness that drives `infer_ctxt`
+// directly, so this can't assert on `start_snapshot`/`rollback_to`/
+// `commit_from` themselves. What it does exercise end-to-end is the
+// one place in this tree that genuinely nests a `try()`: `a` and `b`
+// are still unresolved type variables (each already bound to `int`
+// by the `vec::push` below) at the point the `if` needs their LUB,
+// so `lattice_vars` opens its own `try()` around the candidate bound
+// while the enclosing per-function `commit()` is already open (see
+// `middle::typeck::infer`). The old `commit`, which asserted the undo
+// logs were empty on entry, couldn't tolerate that; `start_snapshot`'s
+// length-based markers can. (An earlier version of this test claimed
+// this exercised speculative method-receiver coercion; no such code
+// exists in this tree, so that was wrong.)
+fn main() {
+    let mut a = [];
+    let mut b = [];
+    vec::push(a, 1);
+    vec::push(b, 2);
+    let w = if true { a } else { b };
+    assert vec::len(w) == 1u;
+}
@@ -0,0 +1,17 @@
+// `r`'s type `{a: T, b: T}` shares one root variable between both
+// fields, so resolving `r` in a single `resolve()` call must hit that
+// root through two different paths. `resolve_ty_var`'s memo table
+// (keyed by union-find root, see `middle::typeck::infer::resolver`)
+// has to return the same cached result on the second path rather than
+// re-walking the chain or, worse, flagging a spurious cycle.
+fn id<T>(x: T) -> T { x }
+
+fn dup<T>(x: T) -> {a: T, b: T} { {a: x, b: x} }
+
+fn main() {
+    let a = 1;
+    let b = id(a);
+    let c = id(b);
+    let r = dup(c);
+    assert r.a + r.b == 2;
+}
@@ -0,0 +1,15 @@
+// Companion to variance-infer.rs: exercises `lub` (not just `sub`)
+// over a covariant parameter. `super_tps` must thread each arm's
+// actual combined type argument into its result instead of always
+// returning the `a`-side's `as` unchanged, or the `if` below would
+// wrongly come out as `cov<@int>` and fail to satisfy `wants_const`.
+enum cov<T> { cov(T) }
+
+fn wants_const(_c: cov<@const int>) {}
+
+fn main() {
+    let a: cov<@int> = cov(@1);
+    let b: cov<@const int> = cov(@2);
+    let w = if true { a } else { b };
+    wants_const(w);
+}
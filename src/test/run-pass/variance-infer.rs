@@ -0,0 +1,13 @@
+// Regression test for variance inference (see `infer_variances` in
+// `middle::typeck::variance`). `cov<T>`'s only field is of type `T`,
+// so `T` should be inferred covariant, which must let a `cov<@int>`
+// be used where a `cov<@const int>` is expected without an explicit
+// conversion.
+enum cov<T> { cov(T) }
+
+fn wants_const(_c: cov<@const int>) {}
+
+fn main() {
+    let x: cov<@int> = cov(@1);
+    wants_const(x);
+}